@@ -0,0 +1,425 @@
+//! A safe, real `Iterator` over B-tree leaf entries.
+//!
+//! Descends from the root to the leftmost leaf (or to a caller-supplied
+//! start key), then walks leaf-to-leaf via `hdr.next_addr`/`hdr.prev_addr`,
+//! pinning at most one leaf at a time via [`NodeGuard`].
+
+use std::marker::PhantomData;
+
+use splinterdb::{btree_config, btree_hdr, btree_node, cache, page_type, slice};
+
+use crate::node_ops::{child_addr, find_child_index, find_leaf_index, tuple_key, tuple_message};
+use crate::{GetMode, NodeGuard};
+
+/// An optional, inclusive-or-exclusive upper (or, for `new_rev()`, lower) bound
+/// on the keys a [`BTreeIter`] will yield.
+#[derive(Clone, Copy, Debug)]
+pub enum KeyBound {
+    Included(slice),
+    Excluded(slice),
+    Unbounded,
+}
+
+/// A cursor over leaf entries, in sorted order, between an optional start
+/// key and an optional end [`KeyBound`].
+///
+/// Only one leaf page is pinned at a time, via a [`NodeGuard`]: advancing
+/// past the last entry of a leaf drops that guard before getting the next
+/// (or previous) sibling.
+pub struct BTreeIter<'a> {
+    cache: *mut cache,
+    cfg: *const btree_config,
+    node_type: page_type,
+    current: Option<NodeGuard<'a>>,
+    index: u32,
+    end: KeyBound,
+    rev: bool,
+    _marker: PhantomData<&'a mut cache>,
+}
+
+impl<'a> BTreeIter<'a> {
+    /// # Safety
+    ///
+    /// `cache` must be a valid, live cache, `cfg` must describe the tree
+    /// rooted at `root_addr`, and `root_addr` must be a node address
+    /// previously written by this cache (or zero, for an empty tree).
+    pub unsafe fn new(
+        cache: &'a mut cache,
+        cfg: &'a btree_config,
+        root_addr: u64,
+        node_type: page_type,
+        start_key: Option<slice>,
+        end: KeyBound,
+    ) -> Self {
+        Self::new_with_direction(cache, cfg, root_addr, node_type, start_key, end, false)
+    }
+
+    /// # Safety
+    ///
+    /// Same preconditions as [`BTreeIter::new`]. Walks previous-sibling
+    /// links instead of next-sibling ones, yielding entries in reverse
+    /// sorted order. With no `start_key`, descends to the rightmost leaf
+    /// rather than the leftmost one.
+    pub unsafe fn new_rev(
+        cache: &'a mut cache,
+        cfg: &'a btree_config,
+        root_addr: u64,
+        node_type: page_type,
+        start_key: Option<slice>,
+        end: KeyBound,
+    ) -> Self {
+        Self::new_with_direction(cache, cfg, root_addr, node_type, start_key, end, true)
+    }
+
+    unsafe fn new_with_direction(
+        cache: &'a mut cache,
+        cfg: &'a btree_config,
+        root_addr: u64,
+        node_type: page_type,
+        start_key: Option<slice>,
+        end: KeyBound,
+        rev: bool,
+    ) -> Self {
+        let mut iter = BTreeIter {
+            cache: cache as *mut cache,
+            cfg: cfg as *const btree_config,
+            node_type,
+            current: None,
+            index: 0,
+            end,
+            rev,
+            _marker: PhantomData,
+        };
+
+        if root_addr != 0 {
+            iter.descend_to_leaf(root_addr, start_key);
+        }
+        iter
+    }
+
+    unsafe fn hdr(&self) -> *mut btree_hdr {
+        match &self.current {
+            Some(guard) => **guard,
+            None => std::ptr::null_mut(),
+        }
+    }
+
+    unsafe fn get_page(&mut self, addr: u64) {
+        // Release the current leaf before pinning the next one.
+        self.current = None;
+        let node = btree_node { addr, page: std::ptr::null_mut(), hdr: std::ptr::null_mut() };
+        self.current = Some(NodeGuard::get(&mut *self.cache, node, self.node_type, GetMode::ReadOnly));
+    }
+
+    /// Descend from `root_addr` to the leaf that should hold `start_key`,
+    /// or, when no key is given, to the leftmost leaf (`rev == false`) or
+    /// rightmost leaf (`rev == true`).
+    unsafe fn descend_to_leaf(&mut self, root_addr: u64, start_key: Option<slice>) {
+        let mut addr = root_addr;
+        loop {
+            let node = btree_node { addr, page: std::ptr::null_mut(), hdr: std::ptr::null_mut() };
+            let guard = NodeGuard::get(&mut *self.cache, node, self.node_type, GetMode::ReadOnly);
+            let hdr: *mut btree_hdr = *guard;
+            let num_entries = (*hdr).num_entries;
+
+            if (*hdr).height == 0 {
+                let start = match start_key {
+                    Some(key) => leaf_start_index(find_leaf_index(&*self.cfg, hdr, key), self.rev),
+                    None => Some(if self.rev { num_entries.saturating_sub(1) } else { 0 }),
+                };
+                match start {
+                    Some(i) => {
+                        self.index = i;
+                        self.current = Some(guard);
+                    }
+                    // A reverse scan missed before this leaf's first entry
+                    // entirely; fall back to the previous leaf's last entry.
+                    None => {
+                        let prev_addr = (*hdr).prev_addr;
+                        drop(guard);
+                        if prev_addr != 0 {
+                            self.get_page(prev_addr);
+                            self.index = (*self.hdr()).num_entries.saturating_sub(1);
+                        } else {
+                            self.current = None;
+                        }
+                    }
+                }
+                return;
+            }
+
+            let child_index = match start_key {
+                Some(key) => find_child_index(&*self.cfg, hdr, key),
+                None if self.rev => num_entries.saturating_sub(1),
+                None => 0,
+            };
+            addr = child_addr(&*self.cfg, hdr, child_index);
+            drop(guard);
+        }
+    }
+
+    unsafe fn at_end_bound(&self, key: slice) -> bool {
+        match self.end {
+            KeyBound::Unbounded => false,
+            KeyBound::Included(bound) => {
+                let cmp = splinterdb::btree_key_compare(self.cfg, key, bound);
+                if self.rev { cmp < 0 } else { cmp > 0 }
+            }
+            KeyBound::Excluded(bound) => {
+                let cmp = splinterdb::btree_key_compare(self.cfg, key, bound);
+                if self.rev { cmp <= 0 } else { cmp >= 0 }
+            }
+        }
+    }
+}
+
+/// Decide which slot in a leaf a scan should start at, given the
+/// `find_leaf_index` result for `start_key` and the scan direction.
+///
+/// `find_leaf_index` returns `Ok(i)` for an exact hit or `Err(i)` for the
+/// first entry with key >= `start_key` (i.e. where it would be inserted).
+/// A forward cursor wants that index directly. A reverse cursor wants
+/// `SeekForPrev` semantics: the last entry <= `start_key`, which is `i`
+/// on an exact hit and `i - 1` on a miss. Returns `None` when that falls before this leaf's first
+/// entry, in which case the caller must fall back to the previous leaf.
+fn leaf_start_index(result: Result<u32, u32>, rev: bool) -> Option<u32> {
+    match result {
+        Ok(i) => Some(i),
+        Err(i) if !rev => Some(i),
+        Err(0) => None,
+        Err(i) => Some(i - 1),
+    }
+}
+
+impl<'a> Iterator for BTreeIter<'a> {
+    type Item = (slice, slice);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            // A run of empty sibling leaves (e.g. after deletions that
+            // haven't been compacted yet) must not grow the call stack, so
+            // this loops rather than recursing into another `next()` call.
+            // Both directions can see a run of these, so this mirrors
+            // `next_addr`/`prev_addr` symmetrically rather than giving up
+            // the moment a single reverse-direction leaf is empty.
+            let hdr = loop {
+                let hdr = self.hdr();
+                if hdr.is_null() {
+                    return None;
+                }
+
+                let num_entries = (*hdr).num_entries;
+                if self.index < num_entries {
+                    break hdr;
+                }
+
+                let sibling_addr = if self.rev { (*hdr).prev_addr } else { (*hdr).next_addr };
+                if sibling_addr == 0 {
+                    self.current = None;
+                    return None;
+                }
+                self.get_page(sibling_addr);
+                self.index = if self.rev { (*self.hdr()).num_entries.saturating_sub(1) } else { 0 };
+            };
+
+            let key = tuple_key(&*self.cfg, hdr, self.index);
+            if self.at_end_bound(key) {
+                self.current = None;
+                return None;
+            }
+            let message = tuple_message(&*self.cfg, hdr, self.index);
+
+            if self.rev {
+                if self.index == 0 {
+                    let prev_addr = (*hdr).prev_addr;
+                    if prev_addr != 0 {
+                        self.get_page(prev_addr);
+                        self.index = (*self.hdr()).num_entries.saturating_sub(1);
+                    } else {
+                        self.current = None;
+                    }
+                } else {
+                    self.index -= 1;
+                }
+            } else {
+                self.index += 1;
+            }
+
+            Some((key, message))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{self, NodeSpec};
+
+    use super::*;
+
+    /// `leaf_start_index` is the only part of `descend_to_leaf`'s key-miss
+    /// handling that needs real entry content to exercise end-to-end
+    /// (`find_leaf_index` reads tuple keys via the real, unmockable
+    /// `btree_get_tuple_key`/`btree_key_compare` C accessors per
+    /// `test_support`), so it's pulled out as a pure function and tested
+    /// directly against the `Result` shapes `find_leaf_index` can return.
+    #[test]
+    fn leaf_start_index_exact_hit_uses_it_in_both_directions() {
+        assert_eq!(leaf_start_index(Ok(2), false), Some(2));
+        assert_eq!(leaf_start_index(Ok(2), true), Some(2));
+    }
+
+    #[test]
+    fn leaf_start_index_forward_miss_uses_insertion_point() {
+        assert_eq!(leaf_start_index(Err(3), false), Some(3));
+        assert_eq!(leaf_start_index(Err(0), false), Some(0));
+    }
+
+    #[test]
+    fn leaf_start_index_rev_miss_steps_back_one() {
+        // A miss at index 3 means slots 0..3 are all < start_key, so a
+        // SeekForPrev-style reverse cursor should land on slot 2.
+        assert_eq!(leaf_start_index(Err(3), true), Some(2));
+    }
+
+    #[test]
+    fn leaf_start_index_rev_miss_before_first_entry_falls_back() {
+        // A miss at index 0 means every entry in this leaf is >= start_key:
+        // there's nothing to land on here, so the caller must walk to the
+        // previous leaf.
+        assert_eq!(leaf_start_index(Err(0), true), None);
+    }
+
+    #[test]
+    fn empty_tree_yields_nothing() {
+        let mut dummy = std::mem::MaybeUninit::<cache>::uninit();
+        let cfg_dummy = std::mem::MaybeUninit::<btree_config>::uninit();
+        let mut iter = unsafe {
+            BTreeIter::new(
+                &mut *dummy.as_mut_ptr(),
+                &*cfg_dummy.as_ptr(),
+                0,
+                page_type::PAGE_TYPE_BRANCH,
+                None,
+                KeyBound::Unbounded,
+            )
+        };
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn empty_tree_rev_yields_nothing() {
+        let mut dummy = std::mem::MaybeUninit::<cache>::uninit();
+        let cfg_dummy = std::mem::MaybeUninit::<btree_config>::uninit();
+        let mut iter = unsafe {
+            BTreeIter::new_rev(
+                &mut *dummy.as_mut_ptr(),
+                &*cfg_dummy.as_ptr(),
+                0,
+                page_type::PAGE_TYPE_BRANCH,
+                None,
+                KeyBound::Unbounded,
+            )
+        };
+        assert!(iter.next().is_none());
+    }
+
+    /// A single-leaf (height 0) tree: `new_rev()` with no `start_key` must
+    /// land on its last entry, not its first, even though there's only one
+    /// leaf to descend to. This is a regression test for the bug where
+    /// `new_rev()` always descended leftmost regardless of direction.
+    #[test]
+    fn rev_without_start_key_lands_on_last_entry() {
+        test_support::reset();
+        test_support::insert(1, NodeSpec { height: 0, num_entries: 5, next_addr: 0, prev_addr: 0 });
+        let ops = test_support::fake_cache_ops();
+        let mut cc = test_support::fake_cache(&ops);
+        let cfg_dummy = std::mem::MaybeUninit::<btree_config>::uninit();
+
+        {
+            let fwd = unsafe {
+                BTreeIter::new(&mut cc, &*cfg_dummy.as_ptr(), 1, page_type::PAGE_TYPE_BRANCH, None, KeyBound::Unbounded)
+            };
+            assert_eq!(fwd.index, 0);
+        }
+
+        {
+            let rev = unsafe {
+                BTreeIter::new_rev(&mut cc, &*cfg_dummy.as_ptr(), 1, page_type::PAGE_TYPE_BRANCH, None, KeyBound::Unbounded)
+            };
+            assert_eq!(rev.index, 4);
+        }
+    }
+
+    /// Every page fetched while constructing and draining an iterator must
+    /// be pinned read-only: a regression test for the bug where
+    /// `descend_to_leaf` claimed (`TRUE`) every node on the way down.
+    #[test]
+    fn descent_and_scan_never_claim() {
+        test_support::reset();
+        test_support::insert(1, NodeSpec { height: 0, num_entries: 0, next_addr: 0, prev_addr: 0 });
+        let ops = test_support::fake_cache_ops();
+        let mut cc = test_support::fake_cache(&ops);
+        let cfg_dummy = std::mem::MaybeUninit::<btree_config>::uninit();
+
+        let mut iter = unsafe {
+            BTreeIter::new(&mut cc, &*cfg_dummy.as_ptr(), 1, page_type::PAGE_TYPE_BRANCH, None, KeyBound::Unbounded)
+        };
+        assert!(iter.next().is_none());
+        assert_eq!(test_support::SAW_CLAIM.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    /// A long run of empty sibling leaves (e.g. after deletions that
+    /// haven't been compacted) must be walked iteratively: a regression
+    /// test for the self-recursive `return self.next()` that risked a
+    /// stack overflow on a long chain.
+    #[test]
+    fn long_chain_of_empty_leaves_does_not_overflow() {
+        test_support::reset();
+        const CHAIN_LEN: u64 = 10_000;
+        for addr in 1..=CHAIN_LEN {
+            let next_addr = if addr == CHAIN_LEN { 0 } else { addr + 1 };
+            test_support::insert(addr, NodeSpec { height: 0, num_entries: 0, next_addr, prev_addr: 0 });
+        }
+        let ops = test_support::fake_cache_ops();
+        let mut cc = test_support::fake_cache(&ops);
+        let cfg_dummy = std::mem::MaybeUninit::<btree_config>::uninit();
+
+        let mut iter = unsafe {
+            BTreeIter::new(&mut cc, &*cfg_dummy.as_ptr(), 1, page_type::PAGE_TYPE_BRANCH, None, KeyBound::Unbounded)
+        };
+        assert!(iter.next().is_none());
+        assert_eq!(test_support::GETS.load(std::sync::atomic::Ordering::SeqCst), CHAIN_LEN as usize);
+        assert_eq!(test_support::UNGETS.load(std::sync::atomic::Ordering::SeqCst), CHAIN_LEN as usize);
+    }
+
+    /// The same chain-of-empty-leaves walk, but backwards: a regression
+    /// test for `next()` giving up (returning `None`) the moment it saw a
+    /// single empty leaf in a `new_rev()` scan instead of following `prev_addr` the
+    /// way the forward direction follows `next_addr`.
+    #[test]
+    fn long_chain_of_empty_leaves_does_not_overflow_rev() {
+        test_support::reset();
+        const CHAIN_LEN: u64 = 10_000;
+        for addr in 1..=CHAIN_LEN {
+            let prev_addr = if addr == 1 { 0 } else { addr - 1 };
+            test_support::insert(addr, NodeSpec { height: 0, num_entries: 0, next_addr: 0, prev_addr });
+        }
+        let ops = test_support::fake_cache_ops();
+        let mut cc = test_support::fake_cache(&ops);
+        let cfg_dummy = std::mem::MaybeUninit::<btree_config>::uninit();
+
+        let mut iter = unsafe {
+            BTreeIter::new_rev(
+                &mut cc,
+                &*cfg_dummy.as_ptr(),
+                CHAIN_LEN,
+                page_type::PAGE_TYPE_BRANCH,
+                None,
+                KeyBound::Unbounded,
+            )
+        };
+        assert!(iter.next().is_none());
+        assert_eq!(test_support::GETS.load(std::sync::atomic::Ordering::SeqCst), CHAIN_LEN as usize);
+        assert_eq!(test_support::UNGETS.load(std::sync::atomic::Ordering::SeqCst), CHAIN_LEN as usize);
+    }
+}
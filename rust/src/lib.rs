@@ -1,8 +1,24 @@
-use splinterdb::{btree_config, btree_hdr, btree_node, cache, page_type, TRUE};
+use std::ops::Deref;
+
+use splinterdb::{btree_config, btree_hdr, btree_node, cache, page_type, FALSE, TRUE};
 
 /// cbindgen:ignore
 mod splinterdb;
 
+mod node_ops;
+
+#[cfg(test)]
+mod test_support;
+
+mod verify;
+pub use verify::{btree_verify, Finding, Problem, VerifyMode, VerifyReport};
+
+mod iter;
+pub use iter::{BTreeIter, KeyBound};
+
+mod lookup;
+pub use lookup::{btree_lookup, LeafSlot};
+
 /// # Safety
 ///
 /// Probably unsafe.
@@ -17,3 +33,114 @@ pub unsafe extern "C" fn btree_node_get(
     (*node).page = (*(*cc).ops).page_get.unwrap()(cc, (*node).addr, TRUE as i32, type_);
     (*node).hdr = (*(*node).page).data as *mut btree_hdr;
 }
+
+/// Whether a node is pinned for read-only access or for a claim/write.
+///
+/// Controls the `needs_claim` flag passed down to `page_get`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GetMode {
+    ReadOnly,
+    Write,
+}
+
+impl GetMode {
+    fn needs_claim(self) -> i32 {
+        match self {
+            GetMode::ReadOnly => FALSE as i32,
+            GetMode::Write => TRUE as i32,
+        }
+    }
+}
+
+/// A pinned B-tree node that releases its page when dropped.
+///
+/// Runs the same `page_get` logic as `btree_node_get`, but ties the pin to
+/// the guard's lifetime instead of relying on callers to remember to
+/// `page_unget` it themselves.
+pub struct NodeGuard<'a> {
+    cache: &'a mut cache,
+    node: btree_node,
+    type_: page_type,
+}
+
+impl<'a> NodeGuard<'a> {
+    /// # Safety
+    ///
+    /// `cache` must be a valid, live cache and `node.addr` a node address
+    /// previously written by this cache.
+    pub unsafe fn get(
+        cache: &'a mut cache,
+        mut node: btree_node,
+        type_: page_type,
+        mode: GetMode,
+    ) -> Self {
+        debug_assert!(node.addr != 0);
+        node.page = (*(*cache).ops).page_get.unwrap()(cache, node.addr, mode.needs_claim(), type_);
+        node.hdr = (*node.page).data as *mut btree_hdr;
+        NodeGuard { cache, node, type_ }
+    }
+}
+
+impl<'a> Deref for NodeGuard<'a> {
+    type Target = *mut btree_hdr;
+
+    fn deref(&self) -> &*mut btree_hdr {
+        &self.node.hdr
+    }
+}
+
+impl<'a> Drop for NodeGuard<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            (*(*self.cache).ops).page_unget.unwrap()(self.cache, self.node.page, self.type_);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use splinterdb::{cache_ops, page_handle};
+
+    use super::*;
+
+    static GETS: AtomicUsize = AtomicUsize::new(0);
+    static UNGETS: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn fake_page_get(
+        _cc: *mut cache,
+        _addr: u64,
+        _needs_claim: i32,
+        _type_: page_type,
+    ) -> *mut page_handle {
+        GETS.fetch_add(1, Ordering::SeqCst);
+        Box::into_raw(Box::new(unsafe { std::mem::zeroed::<page_handle>() }))
+    }
+
+    extern "C" fn fake_page_unget(_cc: *mut cache, page: *mut page_handle, _type_: page_type) {
+        UNGETS.fetch_add(1, Ordering::SeqCst);
+        unsafe { drop(Box::from_raw(page)) };
+    }
+
+    #[test]
+    fn node_guard_ungets_exactly_once_on_drop() {
+        GETS.store(0, Ordering::SeqCst);
+        UNGETS.store(0, Ordering::SeqCst);
+
+        let ops = cache_ops {
+            page_get: Some(fake_page_get),
+            page_unget: Some(fake_page_unget),
+            ..unsafe { std::mem::zeroed() }
+        };
+        let mut cc = cache { ops: &ops as *const cache_ops as *mut cache_ops, ..unsafe { std::mem::zeroed() } };
+
+        let node = btree_node { addr: 1, page: std::ptr::null_mut(), hdr: std::ptr::null_mut() };
+        let guard = unsafe { NodeGuard::get(&mut cc, node, page_type::PAGE_TYPE_BRANCH, GetMode::ReadOnly) };
+        assert_eq!(GETS.load(Ordering::SeqCst), 1);
+        assert_eq!(UNGETS.load(Ordering::SeqCst), 0);
+
+        drop(guard);
+        assert_eq!(UNGETS.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,127 @@
+//! Keyed point lookup over a B-tree.
+//!
+//! Iteratively descends from a root address to the leaf that would hold a
+//! key, pinning each node through a [`NodeGuard`] at each level, then
+//! binary-searches the leaf itself. Interior nodes are released as soon as
+//! the next child is chosen, so only one node is ever pinned.
+
+use splinterdb::{btree_config, btree_hdr, btree_node, cache, page_type, slice};
+
+use crate::node_ops::{child_addr, find_child_index, find_leaf_index};
+use crate::{GetMode, NodeGuard};
+
+/// The resolved position of a key within a leaf: the leaf's node address
+/// and the entry index, whether or not the key was actually present.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LeafSlot {
+    pub leaf_addr: u64,
+    pub index: u32,
+    pub found: bool,
+}
+
+/// Descend from `root_addr` to the leaf that would hold `key`, returning
+/// its address and entry index.
+///
+/// If `key` is present, `LeafSlot::found` is `true` and `index` is its
+/// entry index; otherwise `index` is the position at which it would be
+/// inserted. When `allow_missing` is `false`, a miss is reported as `None`
+/// instead. An empty tree (`root_addr == 0`) has no leaf to report, so it
+/// yields `None` whatever `allow_missing` is.
+///
+/// `node_type` is the `page_type` under which this tree's nodes were
+/// written, same as the type a caller would pass to `btree_node_get`.
+///
+/// # Safety
+///
+/// `cache` must be a valid, live cache, `cfg` must describe the tree
+/// rooted at `root_addr`, and `root_addr` must be a node address
+/// previously written by this cache (or zero, for an empty tree).
+pub unsafe fn btree_lookup(
+    cache: &mut cache,
+    cfg: &btree_config,
+    root_addr: u64,
+    node_type: page_type,
+    key: slice,
+    allow_missing: bool,
+) -> Option<LeafSlot> {
+    if root_addr == 0 {
+        return None;
+    }
+
+    let mut addr = root_addr;
+    loop {
+        let node = btree_node { addr, page: std::ptr::null_mut(), hdr: std::ptr::null_mut() };
+        let guard = NodeGuard::get(cache, node, node_type, GetMode::ReadOnly);
+        let hdr: *mut btree_hdr = *guard;
+
+        if (*hdr).height == 0 {
+            let result = match find_leaf_index(cfg, hdr, key) {
+                Ok(index) => LeafSlot { leaf_addr: addr, index, found: true },
+                Err(index) => LeafSlot { leaf_addr: addr, index, found: false },
+            };
+            drop(guard);
+            return if result.found || allow_missing { Some(result) } else { None };
+        }
+
+        let child_index = find_child_index(cfg, hdr, key);
+        let next = child_addr(cfg, hdr, child_index);
+        drop(guard);
+        if next == 0 {
+            return None;
+        }
+        addr = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{self, NodeSpec};
+
+    use super::*;
+
+    #[test]
+    fn empty_tree_is_always_a_miss() {
+        let mut dummy = std::mem::MaybeUninit::<cache>::uninit();
+        let cfg_dummy = std::mem::MaybeUninit::<btree_config>::uninit();
+        let key_dummy = unsafe { std::mem::zeroed::<slice>() };
+
+        let found = unsafe {
+            btree_lookup(
+                &mut *dummy.as_mut_ptr(),
+                &*cfg_dummy.as_ptr(),
+                0,
+                page_type::PAGE_TYPE_BRANCH,
+                key_dummy,
+                true,
+            )
+        };
+        assert!(found.is_none());
+    }
+
+    /// A single, empty leaf root: a miss with `allow_missing: true` reports
+    /// the leaf address and insertion point 0; with `allow_missing: false`
+    /// it reports `None`. Also a regression test for the pin mode: lookup
+    /// must never claim the nodes it walks.
+    #[test]
+    fn single_empty_leaf_miss() {
+        test_support::reset();
+        test_support::insert(1, NodeSpec { height: 0, num_entries: 0, next_addr: 0, prev_addr: 0 });
+        let ops = test_support::fake_cache_ops();
+        let mut cc = test_support::fake_cache(&ops);
+        let cfg_dummy = std::mem::MaybeUninit::<btree_config>::uninit();
+        let key_dummy = unsafe { std::mem::zeroed::<slice>() };
+
+        let allowed = unsafe {
+            btree_lookup(&mut cc, &*cfg_dummy.as_ptr(), 1, page_type::PAGE_TYPE_BRANCH, key_dummy, true)
+        };
+        assert_eq!(allowed, Some(LeafSlot { leaf_addr: 1, index: 0, found: false }));
+
+        let disallowed = unsafe {
+            btree_lookup(&mut cc, &*cfg_dummy.as_ptr(), 1, page_type::PAGE_TYPE_BRANCH, key_dummy, false)
+        };
+        assert_eq!(disallowed, None);
+
+        assert_eq!(test_support::SAW_CLAIM.load(std::sync::atomic::Ordering::SeqCst), 0);
+        assert_eq!(test_support::GETS.load(std::sync::atomic::Ordering::SeqCst), test_support::UNGETS.load(std::sync::atomic::Ordering::SeqCst));
+    }
+}
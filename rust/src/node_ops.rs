@@ -0,0 +1,81 @@
+//! Small helpers shared by the safe traversal code (`verify`, `iter`, ...)
+//! for reading entries out of a pinned `btree_hdr`.
+
+use splinterdb::{btree_config, btree_hdr, slice};
+
+/// # Safety
+///
+/// `hdr` must be a valid, pinned header with `index < (*hdr).num_entries`.
+pub(crate) unsafe fn pivot_key(cfg: &btree_config, hdr: *mut btree_hdr, index: u32) -> slice {
+    splinterdb::btree_get_pivot(cfg as *const btree_config, hdr, index)
+}
+
+/// # Safety
+///
+/// `hdr` must be a valid, pinned internal-node header with
+/// `index < (*hdr).num_entries`.
+pub(crate) unsafe fn child_addr(cfg: &btree_config, hdr: *mut btree_hdr, index: u32) -> u64 {
+    splinterdb::btree_get_child_addr(cfg as *const btree_config, hdr, index)
+}
+
+/// # Safety
+///
+/// `hdr` must be a valid, pinned leaf header with `index < (*hdr).num_entries`.
+pub(crate) unsafe fn tuple_key(cfg: &btree_config, hdr: *mut btree_hdr, index: u32) -> slice {
+    splinterdb::btree_get_tuple_key(cfg as *const btree_config, hdr, index)
+}
+
+/// # Safety
+///
+/// `hdr` must be a valid, pinned leaf header with `index < (*hdr).num_entries`.
+pub(crate) unsafe fn tuple_message(cfg: &btree_config, hdr: *mut btree_hdr, index: u32) -> slice {
+    splinterdb::btree_get_tuple_message(cfg as *const btree_config, hdr, index)
+}
+
+pub(crate) unsafe fn key_lt(cfg: &btree_config, a: slice, b: slice) -> bool {
+    splinterdb::btree_key_compare(cfg as *const btree_config, a, b) < 0
+}
+
+pub(crate) unsafe fn key_le(cfg: &btree_config, a: slice, b: slice) -> bool {
+    splinterdb::btree_key_compare(cfg as *const btree_config, a, b) <= 0
+}
+
+/// Binary search the separator keys of an internal node for the child that
+/// covers `key`, returning its index. Mirrors the C btree's own
+/// `btree_find_pivot` in spirit: the result is the first separator that is
+/// `>= key`, clamped to the last child if `key` is past every separator.
+pub(crate) unsafe fn find_child_index(cfg: &btree_config, hdr: *mut btree_hdr, key: slice) -> u32 {
+    let num_entries = (*hdr).num_entries;
+    let mut lo = 0u32;
+    let mut hi = num_entries;
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if key_lt(cfg, pivot_key(cfg, hdr, mid), key) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo.min(num_entries.saturating_sub(1))
+}
+
+/// Binary search the keys of a leaf for `key`, returning the matching index
+/// or, on a miss, the index at which it would be inserted.
+pub(crate) unsafe fn find_leaf_index(cfg: &btree_config, hdr: *mut btree_hdr, key: slice) -> Result<u32, u32> {
+    let num_entries = (*hdr).num_entries;
+    let mut lo = 0u32;
+    let mut hi = num_entries;
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if key_lt(cfg, tuple_key(cfg, hdr, mid), key) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    if lo < num_entries && key_le(cfg, key, tuple_key(cfg, hdr, lo)) && key_le(cfg, tuple_key(cfg, hdr, lo), key) {
+        Ok(lo)
+    } else {
+        Err(lo)
+    }
+}
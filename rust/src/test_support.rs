@@ -0,0 +1,82 @@
+//! Shared fake-cache fixture for unit tests.
+//!
+//! Wires a small in-memory registry of fake nodes (keyed by address, with
+//! configurable `height`/`num_entries`/`next_addr`/`prev_addr`) up to the
+//! real `cache_ops` vtable, so `NodeGuard`/`BTreeIter`/`btree_lookup` can
+//! be driven without a real C cache.
+//!
+//! Entry *content* is not faked: decoding it goes through the real,
+//! unmockable `btree_get_*`/`btree_key_compare` C accessors, so these
+//! fixtures are limited to nodes with zero entries (no entry bytes are
+//! ever read) and to inspecting traversal state that only depends on
+//! `btree_hdr`'s plain fields.
+
+#![cfg(test)]
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use splinterdb::{btree_hdr, cache, cache_ops, page_handle, page_type};
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct NodeSpec {
+    pub height: u32,
+    pub num_entries: u32,
+    pub next_addr: u64,
+    pub prev_addr: u64,
+}
+
+thread_local! {
+    static NODES: RefCell<HashMap<u64, NodeSpec>> = RefCell::new(HashMap::new());
+}
+
+pub(crate) static GETS: AtomicUsize = AtomicUsize::new(0);
+pub(crate) static UNGETS: AtomicUsize = AtomicUsize::new(0);
+pub(crate) static SAW_CLAIM: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn reset() {
+    NODES.with(|n| n.borrow_mut().clear());
+    GETS.store(0, Ordering::SeqCst);
+    UNGETS.store(0, Ordering::SeqCst);
+    SAW_CLAIM.store(0, Ordering::SeqCst);
+}
+
+pub(crate) fn insert(addr: u64, spec: NodeSpec) {
+    NODES.with(|n| n.borrow_mut().insert(addr, spec));
+}
+
+extern "C" fn fake_page_get(_cc: *mut cache, addr: u64, needs_claim: i32, _type_: page_type) -> *mut page_handle {
+    GETS.fetch_add(1, Ordering::SeqCst);
+    if needs_claim != 0 {
+        SAW_CLAIM.fetch_add(1, Ordering::SeqCst);
+    }
+
+    let spec = NODES.with(|n| *n.borrow().get(&addr).expect("fake node not registered"));
+    let mut hdr: btree_hdr = unsafe { std::mem::zeroed() };
+    hdr.height = spec.height;
+    hdr.num_entries = spec.num_entries;
+    hdr.next_addr = spec.next_addr;
+    hdr.prev_addr = spec.prev_addr;
+    let hdr_ptr = Box::into_raw(Box::new(hdr));
+
+    let mut page: page_handle = unsafe { std::mem::zeroed() };
+    page.data = hdr_ptr as *mut _;
+    Box::into_raw(Box::new(page))
+}
+
+extern "C" fn fake_page_unget(_cc: *mut cache, page: *mut page_handle, _type_: page_type) {
+    UNGETS.fetch_add(1, Ordering::SeqCst);
+    unsafe {
+        let page = Box::from_raw(page);
+        drop(Box::from_raw(page.data as *mut btree_hdr));
+    }
+}
+
+pub(crate) fn fake_cache_ops() -> cache_ops {
+    cache_ops { page_get: Some(fake_page_get), page_unget: Some(fake_page_unget), ..unsafe { std::mem::zeroed() } }
+}
+
+pub(crate) fn fake_cache(ops: &cache_ops) -> cache {
+    cache { ops: ops as *const cache_ops as *mut cache_ops, ..unsafe { std::mem::zeroed() } }
+}
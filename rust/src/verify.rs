@@ -0,0 +1,220 @@
+//! Structural verification (fsck) for on-disk B-trees.
+//!
+//! Walks a tree from its root and checks the invariants `btree_node_get`
+//! callers otherwise just have to trust: sorted entries, separator keys
+//! that bound their child subtrees, consistent per-level heights, and
+//! resolvable child addresses. Findings are accumulated rather than
+//! aborting on the first problem, so operators can audit a database after
+//! a crash without trusting it blindly.
+
+use std::collections::HashSet;
+
+use splinterdb::{btree_config, btree_hdr, btree_node, cache, page_type, slice};
+
+use crate::node_ops::{child_addr, key_lt, pivot_key, tuple_key};
+use crate::{GetMode, NodeGuard};
+
+/// How a [`btree_verify`] pass should behave once it finds a problem.
+///
+/// Only `DryRun` is implemented today; `Repair` is reserved for a future
+/// pass that patches what it safely can instead of only reporting.
+/// [`btree_verify`] panics if called with `Repair`, in every build profile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifyMode {
+    DryRun,
+    Repair,
+}
+
+/// A single structural problem found at a node address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// Entries within a node were not strictly key-sorted.
+    UnsortedEntries { index: u32 },
+    /// A separator key did not bound the subtree it came down from.
+    SeparatorOutOfRange { index: u32 },
+    /// A child's height was not exactly one less than its parent's.
+    HeightMismatch { expected: u32, found: u32 },
+    /// A child address was zero, so the subtree could not be entered.
+    UnresolvableChild { index: u32 },
+}
+
+/// One finding produced by [`btree_verify`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub addr: u64,
+    pub problem: Problem,
+}
+
+/// The accumulated result of a [`btree_verify`] pass.
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub findings: Vec<Finding>,
+}
+
+impl VerifyReport {
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+struct WorkItem {
+    addr: u64,
+    expected_height: Option<u32>,
+    lower_bound: Option<slice>,
+    upper_bound: Option<slice>,
+}
+
+/// Walk the tree rooted at `root_addr`, collecting structural problems.
+///
+/// `node_type` is the `page_type` under which this tree's nodes were
+/// written, same as the type a caller would pass to `btree_node_get`.
+///
+/// # Safety
+///
+/// `cache` must be a valid, live cache and `root_addr` must be a node
+/// address previously written by this cache (or zero, for an empty tree).
+pub unsafe fn btree_verify(
+    cache: &mut cache,
+    cfg: &btree_config,
+    root_addr: u64,
+    node_type: page_type,
+    mode: VerifyMode,
+) -> VerifyReport {
+    match mode {
+        VerifyMode::DryRun => {}
+        VerifyMode::Repair => unimplemented!("VerifyMode::Repair is not implemented yet"),
+    }
+
+    let mut report = VerifyReport::default();
+    if root_addr == 0 {
+        return report;
+    }
+
+    let mut stack = vec![WorkItem {
+        addr: root_addr,
+        expected_height: None,
+        lower_bound: None,
+        upper_bound: None,
+    }];
+    // Corruption can point a child address back at itself or an ancestor;
+    // without this, such a cycle would make the walk push a WorkItem for
+    // the same node forever instead of terminating.
+    let mut visited: HashSet<u64> = HashSet::new();
+
+    while let Some(item) = stack.pop() {
+        // Every pushed WorkItem already has a nonzero addr: the root is
+        // checked above, and a zero child addr is reported as
+        // UnresolvableChild below without being pushed.
+        debug_assert!(item.addr != 0);
+
+        if !visited.insert(item.addr) {
+            continue;
+        }
+
+        let node = btree_node { addr: item.addr, page: std::ptr::null_mut(), hdr: std::ptr::null_mut() };
+        let guard = NodeGuard::get(cache, node, node_type, GetMode::ReadOnly);
+        let hdr: *mut btree_hdr = *guard;
+
+        let height = (*hdr).height;
+        let num_entries = (*hdr).num_entries;
+
+        if let Some(expected) = item.expected_height {
+            if height != expected {
+                report.findings.push(Finding {
+                    addr: item.addr,
+                    problem: Problem::HeightMismatch { expected, found: height },
+                });
+                // The recorded height can't be trusted, so treating it as
+                // `height - 1` levels above its children would just walk
+                // further into whatever's actually there; stop here
+                // instead of recursing into a subtree we can't interpret.
+                drop(guard);
+                continue;
+            }
+        }
+
+        // Leaf entries are tuples (key, message) and live in a different
+        // on-disk layout than an internal node's separator pivots, so they
+        // need their own accessor rather than `pivot_key`.
+        let entry_key = |index: u32| if height == 0 { tuple_key(cfg, hdr, index) } else { pivot_key(cfg, hdr, index) };
+
+        if num_entries > 0 {
+            let first_key = entry_key(0);
+            let last_key = entry_key(num_entries - 1);
+            if item.lower_bound.map_or(false, |lb| key_lt(cfg, first_key, lb)) {
+                report.findings.push(Finding { addr: item.addr, problem: Problem::SeparatorOutOfRange { index: 0 } });
+            }
+            if item.upper_bound.map_or(false, |ub| key_lt(cfg, ub, last_key)) {
+                report.findings.push(Finding {
+                    addr: item.addr,
+                    problem: Problem::SeparatorOutOfRange { index: num_entries - 1 },
+                });
+            }
+        }
+
+        for index in 1..num_entries {
+            let prev = entry_key(index - 1);
+            let cur = entry_key(index);
+            if !key_lt(cfg, prev, cur) {
+                report.findings.push(Finding { addr: item.addr, problem: Problem::UnsortedEntries { index } });
+            }
+        }
+
+        if height > 0 {
+            for index in 0..num_entries {
+                let addr = child_addr(cfg, hdr, index);
+                if addr == 0 {
+                    report.findings.push(Finding { addr: item.addr, problem: Problem::UnresolvableChild { index } });
+                    continue;
+                }
+                let lower_bound = if index == 0 { item.lower_bound } else { Some(pivot_key(cfg, hdr, index - 1)) };
+                let upper_bound = Some(pivot_key(cfg, hdr, index));
+                stack.push(WorkItem { addr, expected_height: Some(height - 1), lower_bound, upper_bound });
+            }
+        }
+
+        drop(guard);
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_tree_is_clean() {
+        let mut dummy = std::mem::MaybeUninit::<cache>::uninit();
+        let cfg_dummy = std::mem::MaybeUninit::<btree_config>::uninit();
+        let report = unsafe {
+            btree_verify(
+                &mut *dummy.as_mut_ptr(),
+                &*cfg_dummy.as_ptr(),
+                0,
+                page_type::PAGE_TYPE_BRANCH,
+                VerifyMode::DryRun,
+            )
+        };
+        assert!(report.is_clean());
+    }
+
+    /// `Repair` must fail loudly in every build profile rather than
+    /// silently falling through to the dry-run path, so this can't be a
+    /// `debug_assert!` (those compile out in release).
+    #[test]
+    #[should_panic(expected = "not implemented")]
+    fn repair_mode_panics() {
+        let mut dummy = std::mem::MaybeUninit::<cache>::uninit();
+        let cfg_dummy = std::mem::MaybeUninit::<btree_config>::uninit();
+        unsafe {
+            btree_verify(
+                &mut *dummy.as_mut_ptr(),
+                &*cfg_dummy.as_ptr(),
+                0,
+                page_type::PAGE_TYPE_BRANCH,
+                VerifyMode::Repair,
+            );
+        }
+    }
+}